@@ -0,0 +1,93 @@
+//! Small compatibility helpers layered over the platform-specific `sys` socket.
+
+use std::io::Result;
+use std::time::Duration;
+
+use libc::{c_int, timeval};
+
+use crate::sys::Socket;
+
+/// Exposes a reference to the underlying platform socket.
+///
+/// This lets free functions in this module (and tests) operate on the raw
+/// socket without every accessor having to live on `IcmpSocket` itself.
+pub trait AsInner<T> {
+    /// Returns a reference to the wrapped value.
+    fn as_inner(&self) -> &T;
+}
+
+fn dur_to_timeval(dur: Option<Duration>) -> Result<timeval> {
+    match dur {
+        Some(dur) if dur.as_secs() == 0 && dur.subsec_nanos() == 0 => {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "cannot set a zero duration timeout",
+            ))
+        }
+        Some(dur) => Ok(timeval {
+            tv_sec: dur.as_secs() as libc::time_t,
+            tv_usec: (dur.subsec_micros()) as libc::suseconds_t,
+        }),
+        None => Ok(timeval {
+            tv_sec: 0,
+            tv_usec: 0,
+        }),
+    }
+}
+
+fn timeval_to_dur(tv: timeval) -> Option<Duration> {
+    if tv.tv_sec == 0 && tv.tv_usec == 0 {
+        None
+    } else {
+        Some(Duration::new(tv.tv_sec as u64, (tv.tv_usec as u32) * 1000))
+    }
+}
+
+/// Sets a `SO_RCVTIMEO`/`SO_SNDTIMEO`-style timeout option on `sock`.
+pub fn set_timeout(sock: &Socket, dur: Option<Duration>, opt: c_int) -> Result<()> {
+    let tv = dur_to_timeval(dur)?;
+    sock.setsockopt(libc::SOL_SOCKET, opt, tv)
+}
+
+/// Reads back a `SO_RCVTIMEO`/`SO_SNDTIMEO`-style timeout option from `sock`.
+pub fn timeout(sock: &Socket, opt: c_int) -> Result<Option<Duration>> {
+    let tv: timeval = sock.getsockopt(libc::SOL_SOCKET, opt)?;
+    Ok(timeval_to_dur(tv))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dur_to_timeval_rejects_a_zero_duration() {
+        assert!(dur_to_timeval(Some(Duration::new(0, 0))).is_err());
+    }
+
+    #[test]
+    fn dur_to_timeval_of_none_is_a_zeroed_timeval() {
+        let tv = dur_to_timeval(None).unwrap();
+        assert_eq!((tv.tv_sec, tv.tv_usec), (0, 0));
+    }
+
+    #[test]
+    fn dur_to_timeval_splits_seconds_and_microseconds() {
+        let tv = dur_to_timeval(Some(Duration::new(2, 500_000_000))).unwrap();
+        assert_eq!((tv.tv_sec, tv.tv_usec), (2, 500_000));
+    }
+
+    #[test]
+    fn timeval_round_trips_through_duration() {
+        let dur = Some(Duration::new(2, 500_000_000));
+        let tv = dur_to_timeval(dur).unwrap();
+
+        assert_eq!(timeval_to_dur(tv), dur);
+    }
+
+    #[test]
+    fn zeroed_timeval_round_trips_to_none() {
+        let tv = dur_to_timeval(None).unwrap();
+
+        assert_eq!(timeval_to_dur(tv), None);
+    }
+}
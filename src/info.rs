@@ -0,0 +1,64 @@
+//! Per-datagram metadata returned by `recv_with_info`.
+
+use std::net::IpAddr;
+
+/// Extra metadata captured for a single received datagram via ancillary
+/// (control) messages, on top of the bytes copied into the caller's buffer.
+///
+/// This is what makes incremental-TTL traceroute feasible on a single
+/// socket: `ttl` identifies which hop a reply came from, and `error`
+/// surfaces ICMP errors the kernel would otherwise deliver out-of-band via
+/// the socket's error queue.
+#[derive(Debug, Clone)]
+pub struct RecvInfo {
+    /// Number of bytes received into the caller's buffer.
+    pub len: usize,
+    /// Address the datagram was received from.
+    pub source: IpAddr,
+    /// Hop count the datagram arrived with (`IP_RECVTTL`/`IPV6_RECVHOPLIMIT`),
+    /// if the platform reported one.
+    pub ttl: Option<u8>,
+    /// Index of the interface the datagram arrived on
+    /// (`IP_PKTINFO`/`IPV6_PKTINFO`), if reported.
+    pub interface: Option<u32>,
+    /// An ICMP error retrieved from the socket's error queue, if any.
+    pub error: Option<IcmpError>,
+}
+
+/// Flags describing how a vectored receive completed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecvFlags {
+    truncated: bool,
+}
+
+impl RecvFlags {
+    pub(crate) fn new(truncated: bool) -> RecvFlags {
+        RecvFlags { truncated }
+    }
+
+    /// Returns `true` if the received datagram didn't fit in the supplied
+    /// buffers and was clipped (`MSG_TRUNC` on Unix, `WSAEMSGSIZE` on
+    /// Windows).
+    ///
+    /// This matters for ICMP replies carrying an inner IP datagram of
+    /// unpredictable length: a caller can tell a clipped reply apart from a
+    /// genuinely short one.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+/// An ICMP error (e.g. time exceeded, destination unreachable) retrieved
+/// out-of-band from a socket's error queue (`MSG_ERRQUEUE`).
+#[derive(Debug, Clone)]
+pub struct IcmpError {
+    /// Address of the router or host that generated the error.
+    pub source: IpAddr,
+    /// ICMP type of the error.
+    pub kind: u8,
+    /// ICMP code of the error.
+    pub code: u8,
+    /// The offending packet (or as much of it as was captured) that
+    /// triggered the error.
+    pub inner_packet: Vec<u8>,
+}
@@ -3,6 +3,8 @@
 #![deny(missing_docs)]
 
 mod compat;
+pub mod info;
+pub mod message;
 mod socket;
 
 #[cfg(unix)]
@@ -0,0 +1,289 @@
+//! ICMP message types, serialization, and checksum computation.
+//!
+//! This module provides typed builders and parsers for the handful of ICMP
+//! messages most callers need (echo request/reply, and the two common error
+//! messages) so that users of [`IcmpSocket`][crate::IcmpSocket] don't have to
+//! hand-roll headers and checksums themselves.
+
+use std::io::{Error, ErrorKind, Result};
+use std::net::IpAddr;
+
+/// Type/code pair identifying the kind of an ICMP message.
+///
+/// The numeric meaning of a type byte differs between ICMPv4 and ICMPv6, so
+/// this enum distinguishes the two address families explicitly rather than
+/// exposing the raw byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IcmpKind {
+    /// Echo request (ICMPv4 type 8 / ICMPv6 type 128).
+    EchoRequest,
+    /// Echo reply (ICMPv4 type 0 / ICMPv6 type 129).
+    EchoReply,
+    /// Time exceeded, e.g. TTL expired in transit (ICMPv4 type 11 / ICMPv6 type 3).
+    TimeExceeded,
+    /// Destination unreachable (ICMPv4 type 3 / ICMPv6 type 1).
+    DestinationUnreachable,
+    /// Any other type/code not modeled by this crate.
+    Other(u8, u8),
+}
+
+impl IcmpKind {
+    fn from_byte(ty: u8, code: u8, is_v6: bool) -> IcmpKind {
+        match (is_v6, ty) {
+            (false, 8) | (true, 128) => IcmpKind::EchoRequest,
+            (false, 0) | (true, 129) => IcmpKind::EchoReply,
+            (false, 11) | (true, 3) => IcmpKind::TimeExceeded,
+            (false, 3) | (true, 1) => IcmpKind::DestinationUnreachable,
+            _ => IcmpKind::Other(ty, code),
+        }
+    }
+
+    fn type_byte(self, is_v6: bool) -> u8 {
+        match (self, is_v6) {
+            (IcmpKind::EchoRequest, false) => 8,
+            (IcmpKind::EchoRequest, true) => 128,
+            (IcmpKind::EchoReply, false) => 0,
+            (IcmpKind::EchoReply, true) => 129,
+            (IcmpKind::TimeExceeded, false) => 11,
+            (IcmpKind::TimeExceeded, true) => 3,
+            (IcmpKind::DestinationUnreachable, false) => 3,
+            (IcmpKind::DestinationUnreachable, true) => 1,
+            (IcmpKind::Other(ty, _), _) => ty,
+        }
+    }
+}
+
+/// Computes the ICMP checksum over `data`, exactly as given.
+///
+/// The checksum field (bytes 2 and 3) is summed like any other word, so it
+/// must be zeroed before computing a checksum to store; summing a message
+/// whose correct checksum is already in place yields 0.
+///
+/// `data` is summed as a sequence of big-endian 16-bit words with carries
+/// folded back into the low 16 bits, then the one's complement of the
+/// result is returned. If `data` has an odd length, the final byte is
+/// padded with a trailing zero for the purposes of the sum.
+pub fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = 0;
+    let mut chunks = data.chunks_exact(2);
+
+    for chunk in &mut chunks {
+        sum += u32::from(u16::from_be_bytes([chunk[0], chunk[1]]));
+    }
+
+    if let [last] = *chunks.remainder() {
+        sum += u32::from(u16::from_be_bytes([last, 0]));
+    }
+
+    while (sum >> 16) != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !sum as u16
+}
+
+fn write_header(buf: &mut Vec<u8>, kind: IcmpKind, is_v6: bool, code: u8, rest_of_header: u32) {
+    buf.push(kind.type_byte(is_v6));
+    buf.push(code);
+    buf.extend_from_slice(&0u16.to_be_bytes());
+    buf.extend_from_slice(&rest_of_header.to_be_bytes());
+}
+
+fn finalize_checksum(buf: &mut [u8], is_v6: bool) {
+    // The kernel fills in the ICMPv6 checksum itself (it needs the IPv6
+    // pseudo-header, which isn't available here), so leave it zeroed and
+    // let the socket layer know to skip it.
+    if is_v6 {
+        return;
+    }
+
+    let sum = checksum(buf);
+    buf[2..4].copy_from_slice(&sum.to_be_bytes());
+}
+
+/// Strips the IPv4 header a `SOCK_RAW`/`IPPROTO_ICMP` read prepends to the
+/// ICMP message, using the header's IHL field to find where it ends.
+///
+/// ICMPv6 raw sockets don't include the IPv6 header, so this is only needed
+/// for IPv4.
+fn strip_ipv4_header(buf: &[u8]) -> Result<&[u8]> {
+    if buf.is_empty() {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "empty IPv4 datagram"));
+    }
+
+    let header_len = (buf[0] & 0x0f) as usize * 4;
+
+    if buf.len() < header_len {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "IPv4 header longer than the received datagram",
+        ));
+    }
+
+    Ok(&buf[header_len..])
+}
+
+fn parse_header(buf: &[u8]) -> Result<(u8, u8, u16, u32)> {
+    if buf.len() < 8 {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "ICMP message shorter than the 8-byte header",
+        ));
+    }
+
+    let ty = buf[0];
+    let code = buf[1];
+    let received_checksum = u16::from_be_bytes([buf[2], buf[3]]);
+    let rest_of_header = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
+
+    Ok((ty, code, received_checksum, rest_of_header))
+}
+
+fn verify_checksum(buf: &[u8], received: u16, is_v6: bool) -> Result<()> {
+    // ICMPv6 checksums are validated by the kernel against the pseudo-header
+    // before the packet ever reaches userspace, so there is nothing to
+    // re-check here.
+    if is_v6 {
+        return Ok(());
+    }
+
+    let mut zeroed = buf.to_vec();
+    zeroed[2..4].copy_from_slice(&0u16.to_be_bytes());
+
+    if checksum(&zeroed) != received {
+        return Err(Error::new(ErrorKind::InvalidData, "ICMP checksum mismatch"));
+    }
+
+    Ok(())
+}
+
+/// An ICMP echo request ("ping").
+#[derive(Debug, Clone)]
+pub struct EchoRequest {
+    /// Identifier, typically used to distinguish pings from the same process.
+    pub ident: u16,
+    /// Sequence number, typically incremented for each ping sent.
+    pub seq: u16,
+    /// Opaque payload echoed back unmodified by the peer.
+    pub payload: Vec<u8>,
+}
+
+impl EchoRequest {
+    /// Serializes this echo request to bytes addressed to `dst`.
+    ///
+    /// The checksum is computed for ICMPv4 destinations and left zeroed for
+    /// ICMPv6, since the kernel computes it there.
+    pub fn to_bytes(&self, dst: IpAddr) -> Vec<u8> {
+        let is_v6 = dst.is_ipv6();
+        let rest_of_header = (u32::from(self.ident) << 16) | u32::from(self.seq);
+
+        let mut buf = Vec::with_capacity(8 + self.payload.len());
+        write_header(&mut buf, IcmpKind::EchoRequest, is_v6, 0, rest_of_header);
+        buf.extend_from_slice(&self.payload);
+        finalize_checksum(&mut buf, is_v6);
+        buf
+    }
+}
+
+/// An ICMP echo reply.
+#[derive(Debug, Clone)]
+pub struct EchoReply {
+    /// Identifier copied from the originating echo request.
+    pub ident: u16,
+    /// Sequence number copied from the originating echo request.
+    pub seq: u16,
+    /// Payload copied from the originating echo request.
+    pub payload: Vec<u8>,
+}
+
+impl EchoReply {
+    /// Parses an echo reply out of `buf`, received from `src`.
+    ///
+    /// Returns an error if the message is too short, the checksum doesn't
+    /// match, or the type byte isn't an echo reply for `src`'s address family.
+    pub fn from_bytes(buf: &[u8], src: IpAddr) -> Result<EchoReply> {
+        let is_v6 = src.is_ipv6();
+        let buf = if is_v6 { buf } else { strip_ipv4_header(buf)? };
+        let (ty, code, received_checksum, rest_of_header) = parse_header(buf)?;
+        verify_checksum(buf, received_checksum, is_v6)?;
+
+        if IcmpKind::from_byte(ty, code, is_v6) != IcmpKind::EchoReply {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "not an ICMP echo reply",
+            ));
+        }
+
+        Ok(EchoReply {
+            ident: (rest_of_header >> 16) as u16,
+            seq: rest_of_header as u16,
+            payload: buf[8..].to_vec(),
+        })
+    }
+}
+
+/// An ICMP "time exceeded" error, e.g. a TTL expiring in transit.
+#[derive(Debug, Clone)]
+pub struct TimeExceeded {
+    /// Code further qualifying the error (e.g. TTL exceeded in transit vs.
+    /// fragment reassembly time exceeded).
+    pub code: u8,
+    /// The inner IP datagram (or as much of it as the router returned) that
+    /// triggered this error.
+    pub inner_packet: Vec<u8>,
+}
+
+impl TimeExceeded {
+    /// Parses a time-exceeded message out of `buf`, received from `src`.
+    pub fn from_bytes(buf: &[u8], src: IpAddr) -> Result<TimeExceeded> {
+        let is_v6 = src.is_ipv6();
+        let buf = if is_v6 { buf } else { strip_ipv4_header(buf)? };
+        let (ty, code, received_checksum, _) = parse_header(buf)?;
+        verify_checksum(buf, received_checksum, is_v6)?;
+
+        if IcmpKind::from_byte(ty, code, is_v6) != IcmpKind::TimeExceeded {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "not an ICMP time exceeded message",
+            ));
+        }
+
+        Ok(TimeExceeded {
+            code,
+            inner_packet: buf[8..].to_vec(),
+        })
+    }
+}
+
+/// An ICMP "destination unreachable" error.
+#[derive(Debug, Clone)]
+pub struct DestinationUnreachable {
+    /// Code further qualifying why the destination is unreachable (e.g. host
+    /// unreachable vs. port unreachable).
+    pub code: u8,
+    /// The inner IP datagram (or as much of it as the router returned) that
+    /// triggered this error.
+    pub inner_packet: Vec<u8>,
+}
+
+impl DestinationUnreachable {
+    /// Parses a destination-unreachable message out of `buf`, received from `src`.
+    pub fn from_bytes(buf: &[u8], src: IpAddr) -> Result<DestinationUnreachable> {
+        let is_v6 = src.is_ipv6();
+        let buf = if is_v6 { buf } else { strip_ipv4_header(buf)? };
+        let (ty, code, received_checksum, _) = parse_header(buf)?;
+        verify_checksum(buf, received_checksum, is_v6)?;
+
+        if IcmpKind::from_byte(ty, code, is_v6) != IcmpKind::DestinationUnreachable {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "not an ICMP destination unreachable message",
+            ));
+        }
+
+        Ok(DestinationUnreachable {
+            code,
+            inner_packet: buf[8..].to_vec(),
+        })
+    }
+}
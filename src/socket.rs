@@ -1,9 +1,16 @@
 
 use std::net::IpAddr;
-use std::io::{Result};
+use std::io::{IoSlice, IoSliceMut, Result};
 use std::time::Duration;
 
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsRawSocket, RawSocket};
+
 use crate::compat::{AsInner, set_timeout, timeout};
+use crate::info::{RecvFlags, RecvInfo};
+use crate::message::{EchoReply, EchoRequest};
 use crate::sys::Socket;
 
 /// An Internet Control Message Protocol socket.
@@ -40,6 +47,7 @@ use crate::sys::Socket;
 //
 pub struct IcmpSocket {
     inner: Socket,
+    peer: Option<IpAddr>,
 }
 
 impl IcmpSocket {
@@ -50,9 +58,23 @@ impl IcmpSocket {
 
         Ok(IcmpSocket {
             inner,
+            peer: Some(addr),
         })
     }
 
+    /// Creates an ICMP socket bound to the local address `addr`, without
+    /// connecting it to any single peer.
+    ///
+    /// Unlike [`connect`][IcmpSocket::connect], a bound socket can exchange
+    /// datagrams with any number of peers via [`send_to`][IcmpSocket::send_to]
+    /// and [`recv_from`][IcmpSocket::recv_from] — useful for a ping sweep or
+    /// a monitor that talks to many hosts from a single file descriptor.
+    pub fn bind(addr: IpAddr) -> Result<IcmpSocket> {
+        let inner = Socket::bind(addr)?;
+
+        Ok(IcmpSocket { inner, peer: None })
+    }
+
     /// Receives data from the socket. On success, returns the number of bytes read.
     pub fn recv(&self, buf: &mut [u8]) -> Result<usize> {
         self.inner.recv(buf)
@@ -72,6 +94,78 @@ impl IcmpSocket {
         self.inner.send(buf)
     }
 
+    /// Sends data on the socket to `dst`, without requiring the socket to be
+    /// connected.
+    ///
+    /// This pairs with [`recv_from`][IcmpSocket::recv_from] and
+    /// [`bind`][IcmpSocket::bind], letting a single socket exchange
+    /// datagrams with many peers.
+    pub fn send_to(&mut self, buf: &[u8], dst: IpAddr) -> Result<usize> {
+        self.inner.send_to(buf, dst)
+    }
+
+    /// Builds and sends an ICMP echo request to the connected peer.
+    ///
+    /// `ident` and `seq` populate the identifier and sequence number of the
+    /// echo request's rest-of-header, and `payload` is echoed back unmodified
+    /// by the peer. On success, returns the number of bytes sent.
+    ///
+    /// Returns an error if the socket was created with
+    /// [`bind`][IcmpSocket::bind] rather than
+    /// [`connect`][IcmpSocket::connect].
+    pub fn send_echo(&mut self, ident: u16, seq: u16, payload: &[u8]) -> Result<usize> {
+        let peer = self.require_peer()?;
+        let request = EchoRequest {
+            ident,
+            seq,
+            payload: payload.to_vec(),
+        };
+
+        self.send(&request.to_bytes(peer))
+    }
+
+    /// Sends `bufs` as a single datagram without copying them into a
+    /// contiguous buffer first.
+    ///
+    /// This lets a caller emit a prebuilt ICMP header and a separate
+    /// payload slice in one call.
+    pub fn send_vectored(&mut self, bufs: &[IoSlice]) -> Result<usize> {
+        self.inner.send_vectored(bufs)
+    }
+
+    /// Receives a datagram into `bufs` without requiring a single
+    /// contiguous buffer.
+    ///
+    /// The returned [`RecvFlags`] reports whether the datagram was
+    /// truncated to fit — useful since ICMP replies can embed a full inner
+    /// IP datagram of unpredictable length.
+    pub fn recv_vectored(&self, bufs: &mut [IoSliceMut]) -> Result<(usize, RecvFlags)> {
+        self.inner.recv_vectored(bufs)
+    }
+
+    /// Receives and parses an ICMP echo reply from the connected peer.
+    ///
+    /// This is a thin wrapper over [`recv`][IcmpSocket::recv] that validates
+    /// the checksum and rejects anything that isn't an echo reply.
+    ///
+    /// Returns an error if the socket was created with
+    /// [`bind`][IcmpSocket::bind] rather than
+    /// [`connect`][IcmpSocket::connect].
+    pub fn recv_echo(&self, buf: &mut [u8]) -> Result<EchoReply> {
+        let peer = self.require_peer()?;
+        let len = self.recv(buf)?;
+        EchoReply::from_bytes(&buf[..len], peer)
+    }
+
+    fn require_peer(&self) -> Result<IpAddr> {
+        self.peer.ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                "socket was not connected to a peer; use send_to/recv_from instead",
+            )
+        })
+    }
+
     /// Sets the read timeout to the timeout specified.
     ///
     /// If the value specified is `None`, then `read` calls will block
@@ -168,6 +262,52 @@ impl IcmpSocket {
         self.inner.qos()
     }
 
+    /// Receives a datagram along with its TTL, arrival interface, and any
+    /// pending ICMP error (time exceeded, destination unreachable). See
+    /// [`RecvInfo`] for what each field is useful for.
+    pub fn recv_with_info(&self, buf: &mut [u8]) -> Result<RecvInfo> {
+        self.inner.recv_with_info(buf)
+    }
+
+    /// Joins the multicast group `group` on the interface identified by
+    /// `interface` (an interface index).
+    pub fn join_multicast(&self, group: IpAddr, interface: u32) -> Result<()> {
+        self.inner.join_multicast(group, interface)
+    }
+
+    /// Leaves the multicast group `group` on the interface identified by
+    /// `interface` (an interface index).
+    pub fn leave_multicast(&self, group: IpAddr, interface: u32) -> Result<()> {
+        self.inner.leave_multicast(group, interface)
+    }
+
+    /// Sets whether outgoing multicast packets are looped back to this host.
+    pub fn set_multicast_loop(&self, loop_back: bool) -> Result<()> {
+        self.inner.set_multicast_loop(loop_back)
+    }
+
+    /// Sets the hop limit (`IPV6_MULTICAST_HOPS`) applied to outgoing
+    /// multicast packets.
+    pub fn set_multicast_hops(&self, hops: u32) -> Result<()> {
+        self.inner.set_multicast_hops(hops)
+    }
+
+    /// Sets the hop limit (`IPV6_UNICAST_HOPS`) applied to outgoing unicast
+    /// packets.
+    pub fn set_unicast_hops(&self, hops: u32) -> Result<()> {
+        self.inner.set_unicast_hops(hops)
+    }
+
+    /// Moves this socket into or out of non-blocking mode.
+    ///
+    /// When enabled, `recv`/`send` and their variants return an error of
+    /// kind `WouldBlock` instead of blocking, so the socket can be driven by
+    /// an async runtime's reactor (e.g. registered as an mio `SourceFd` or
+    /// tokio `AsyncFd`) rather than parking a thread.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        self.inner.set_nonblocking(nonblocking)
+    }
+
 }
 
 impl AsInner<Socket> for IcmpSocket {
@@ -175,3 +315,17 @@ impl AsInner<Socket> for IcmpSocket {
         &self.inner
     }
 }
+
+#[cfg(unix)]
+impl AsRawFd for IcmpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+#[cfg(windows)]
+impl AsRawSocket for IcmpSocket {
+    fn as_raw_socket(&self) -> RawSocket {
+        self.inner.as_raw_socket()
+    }
+}
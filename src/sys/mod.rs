@@ -0,0 +1,612 @@
+//! Windows raw-socket backing for [`IcmpSocket`][crate::IcmpSocket].
+
+use std::io::{Error, IoSlice, IoSliceMut, Result};
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::windows::io::RawSocket;
+use std::ptr;
+
+use winapi::ctypes::{c_int, c_void};
+use winapi::shared::ws2def::{
+    AF_INET, AF_INET6, SOCKADDR, SOCKADDR_IN, SOCKADDR_STORAGE, SOL_SOCKET, WSABUF, WSAMSG,
+};
+use winapi::shared::ws2ipdef::SOCKADDR_IN6;
+use winapi::shared::winerror::WSAEMSGSIZE;
+use winapi::um::winsock2;
+
+use crate::info::{RecvFlags, RecvInfo};
+
+fn last_os_error() -> Error {
+    Error::last_os_error()
+}
+
+fn family_of(addr: IpAddr) -> c_int {
+    match addr {
+        IpAddr::V4(_) => AF_INET as c_int,
+        IpAddr::V6(_) => AF_INET6 as c_int,
+    }
+}
+
+fn protocol_of(addr: IpAddr) -> c_int {
+    match addr {
+        IpAddr::V4(_) => winapi::shared::ws2def::IPPROTO_ICMP as c_int,
+        IpAddr::V6(_) => winapi::shared::ws2def::IPPROTO_ICMPV6 as c_int,
+    }
+}
+
+/// Writes `addr` into a `SOCKADDR_STORAGE` big enough for either address
+/// family, returning it alongside the length of the address actually
+/// written so callers don't have to know the concrete `SOCKADDR_IN[6]` type.
+fn ip_to_sockaddr(addr: IpAddr) -> (SOCKADDR_STORAGE, i32) {
+    let mut storage: SOCKADDR_STORAGE = unsafe { mem::zeroed() };
+
+    let len = match addr {
+        IpAddr::V4(v4) => unsafe {
+            let sin = &mut *(&mut storage as *mut SOCKADDR_STORAGE as *mut SOCKADDR_IN);
+            sin.sin_family = AF_INET as u16;
+            *sin.sin_addr.S_un.S_addr_mut() = u32::from_ne_bytes(v4.octets());
+            mem::size_of::<SOCKADDR_IN>()
+        },
+        IpAddr::V6(v6) => unsafe {
+            let sin6 = &mut *(&mut storage as *mut SOCKADDR_STORAGE as *mut SOCKADDR_IN6);
+            sin6.sin6_family = AF_INET6 as u16;
+            sin6.sin6_addr.u.Byte_mut().copy_from_slice(&v6.octets());
+            mem::size_of::<SOCKADDR_IN6>()
+        },
+    };
+
+    (storage, len as i32)
+}
+
+fn sockaddr_to_ip(storage: &SOCKADDR_STORAGE, len: i32) -> Result<IpAddr> {
+    match storage.ss_family as c_int {
+        fam if fam == AF_INET as c_int && len as usize >= mem::size_of::<SOCKADDR_IN>() => unsafe {
+            let sin = &*(storage as *const SOCKADDR_STORAGE as *const SOCKADDR_IN);
+            Ok(IpAddr::V4(Ipv4Addr::from(*sin.sin_addr.S_un.S_addr())))
+        },
+        fam if fam == AF_INET6 as c_int && len as usize >= mem::size_of::<SOCKADDR_IN6>() => unsafe {
+            let sin6 = &*(storage as *const SOCKADDR_STORAGE as *const SOCKADDR_IN6);
+            Ok(IpAddr::V6(Ipv6Addr::from(*sin6.sin6_addr.u.Byte())))
+        },
+        _ => Err(Error::new(
+            std::io::ErrorKind::InvalidData,
+            "unrecognized sockaddr family",
+        )),
+    }
+}
+
+/// A raw ICMP socket.
+pub struct Socket {
+    sock: winsock2::SOCKET,
+    family: c_int,
+}
+
+impl Socket {
+    fn new(family: c_int, protocol: c_int) -> Result<Socket> {
+        let sock = unsafe { winsock2::socket(family, winsock2::SOCK_RAW, protocol) };
+
+        if sock == winsock2::INVALID_SOCKET {
+            return Err(last_os_error());
+        }
+
+        Ok(Socket { sock, family })
+    }
+
+    /// Creates a socket and connects it to `addr`.
+    pub fn connect(addr: IpAddr) -> Result<Socket> {
+        let socket = Socket::new(family_of(addr), protocol_of(addr))?;
+        let (sa, len) = ip_to_sockaddr(addr);
+
+        let ret = unsafe { winsock2::connect(socket.sock, &sa as *const SOCKADDR_STORAGE as *const SOCKADDR, len) };
+
+        if ret != 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(socket)
+    }
+
+    /// Creates a socket bound to the local address `addr`, without
+    /// connecting it to any peer.
+    pub fn bind(addr: IpAddr) -> Result<Socket> {
+        let socket = Socket::new(family_of(addr), protocol_of(addr))?;
+        let (sa, len) = ip_to_sockaddr(addr);
+
+        let ret = unsafe { winsock2::bind(socket.sock, &sa as *const SOCKADDR_STORAGE as *const SOCKADDR, len) };
+
+        if ret != 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(socket)
+    }
+
+    pub fn send(&self, buf: &[u8]) -> Result<usize> {
+        let ret = unsafe {
+            winsock2::send(self.sock, buf.as_ptr() as *const i8, buf.len() as i32, 0)
+        };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(ret as usize)
+    }
+
+    /// Sends `buf` to `dst`, without requiring the socket to be connected.
+    pub fn send_to(&self, buf: &[u8], dst: IpAddr) -> Result<usize> {
+        let (sa, len) = ip_to_sockaddr(dst);
+
+        let ret = unsafe {
+            winsock2::sendto(
+                self.sock,
+                buf.as_ptr() as *const i8,
+                buf.len() as i32,
+                0,
+                &sa as *const SOCKADDR_STORAGE as *const SOCKADDR,
+                len,
+            )
+        };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(ret as usize)
+    }
+
+    pub fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        let ret = unsafe {
+            winsock2::recv(self.sock, buf.as_mut_ptr() as *mut i8, buf.len() as i32, 0)
+        };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(ret as usize)
+    }
+
+    pub fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, IpAddr)> {
+        let mut storage: SOCKADDR_STORAGE = unsafe { mem::zeroed() };
+        let mut len = mem::size_of::<SOCKADDR_STORAGE>() as i32;
+
+        let ret = unsafe {
+            winsock2::recvfrom(
+                self.sock,
+                buf.as_mut_ptr() as *mut i8,
+                buf.len() as i32,
+                0,
+                &mut storage as *mut SOCKADDR_STORAGE as *mut SOCKADDR,
+                &mut len as *mut i32,
+            )
+        };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        let addr = sockaddr_to_ip(&storage, len)?;
+        Ok((ret as usize, addr))
+    }
+
+    pub(crate) fn setsockopt<T>(&self, level: c_int, opt: c_int, value: T) -> Result<()> {
+        let ret = unsafe {
+            winsock2::setsockopt(
+                self.sock,
+                level,
+                opt,
+                &value as *const T as *const i8,
+                mem::size_of::<T>() as c_int,
+            )
+        };
+
+        if ret != 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn getsockopt<T: Copy>(&self, level: c_int, opt: c_int) -> Result<T> {
+        unsafe {
+            let mut value: T = mem::zeroed();
+            let mut len = mem::size_of::<T>() as c_int;
+
+            let ret = winsock2::getsockopt(
+                self.sock,
+                level,
+                opt,
+                &mut value as *mut T as *mut i8,
+                &mut len as *mut c_int,
+            );
+
+            if ret != 0 {
+                return Err(last_os_error());
+            }
+
+            Ok(value)
+        }
+    }
+
+    pub fn set_ttl(&self, ttl: u32) -> Result<()> {
+        match self.family {
+            fam if fam == AF_INET as c_int => {
+                self.setsockopt(winapi::shared::ws2def::IPPROTO_IP as c_int, winapi::shared::ws2ipdef::IP_TTL, ttl as c_int)
+            }
+            _ => self.setsockopt(
+                winapi::shared::ws2def::IPPROTO_IPV6 as c_int,
+                winapi::shared::ws2ipdef::IPV6_UNICAST_HOPS,
+                ttl as c_int,
+            ),
+        }
+    }
+
+    pub fn ttl(&self) -> Result<u32> {
+        let ttl: c_int = match self.family {
+            fam if fam == AF_INET as c_int => {
+                self.getsockopt(winapi::shared::ws2def::IPPROTO_IP as c_int, winapi::shared::ws2ipdef::IP_TTL)?
+            }
+            _ => self.getsockopt(
+                winapi::shared::ws2def::IPPROTO_IPV6 as c_int,
+                winapi::shared::ws2ipdef::IPV6_UNICAST_HOPS,
+            )?,
+        };
+
+        Ok(ttl as u32)
+    }
+
+    pub fn set_broadcast(&self, broadcast: bool) -> Result<()> {
+        self.setsockopt(SOL_SOCKET, winsock2::SO_BROADCAST, broadcast as c_int)
+    }
+
+    pub fn broadcast(&self) -> Result<bool> {
+        let broadcast: c_int = self.getsockopt(SOL_SOCKET, winsock2::SO_BROADCAST)?;
+        Ok(broadcast != 0)
+    }
+
+    /// Enables or disables non-blocking mode via `ioctlsocket`/`FIONBIO`.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        let mut mode: winapi::ctypes::c_ulong = if nonblocking { 1 } else { 0 };
+
+        let ret = unsafe { winsock2::ioctlsocket(self.sock, winsock2::FIONBIO, &mut mode) };
+
+        if ret != 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(())
+    }
+
+    pub fn set_qos(&self, qos: u8) -> Result<()> {
+        match self.family {
+            fam if fam == AF_INET as c_int => {
+                self.setsockopt(winapi::shared::ws2def::IPPROTO_IP as c_int, winapi::shared::ws2ipdef::IP_TOS, qos as c_int)
+            }
+            _ => self.setsockopt(
+                winapi::shared::ws2def::IPPROTO_IPV6 as c_int,
+                winapi::shared::ws2ipdef::IPV6_TCLASS,
+                qos as c_int,
+            ),
+        }
+    }
+
+    pub fn qos(&self) -> Result<u8> {
+        let qos: c_int = match self.family {
+            fam if fam == AF_INET as c_int => {
+                self.getsockopt(winapi::shared::ws2def::IPPROTO_IP as c_int, winapi::shared::ws2ipdef::IP_TOS)?
+            }
+            _ => self.getsockopt(
+                winapi::shared::ws2def::IPPROTO_IPV6 as c_int,
+                winapi::shared::ws2ipdef::IPV6_TCLASS,
+            )?,
+        };
+
+        Ok(qos as u8)
+    }
+
+    /// Joins the multicast group `group` on interface `interface` (an
+    /// interface index), setting `IP_ADD_MEMBERSHIP` for IPv4 or
+    /// `IPV6_JOIN_GROUP` for IPv6.
+    pub fn join_multicast(&self, group: IpAddr, interface: u32) -> Result<()> {
+        match group {
+            IpAddr::V4(v4) => {
+                let mreq = winapi::shared::ws2ipdef::IP_MREQ {
+                    imr_multiaddr: unsafe { mem::transmute(v4.octets()) },
+                    imr_interface: unsafe { mem::transmute(interface.to_be_bytes()) },
+                };
+                self.setsockopt(
+                    winapi::shared::ws2def::IPPROTO_IP as c_int,
+                    winapi::shared::ws2ipdef::IP_ADD_MEMBERSHIP,
+                    mreq,
+                )
+            }
+            IpAddr::V6(v6) => {
+                let mreq = winapi::shared::ws2ipdef::IPV6_MREQ {
+                    ipv6mr_multiaddr: unsafe { mem::transmute(v6.octets()) },
+                    ipv6mr_interface: interface,
+                };
+                self.setsockopt(
+                    winapi::shared::ws2def::IPPROTO_IPV6 as c_int,
+                    winapi::shared::ws2ipdef::IPV6_JOIN_GROUP,
+                    mreq,
+                )
+            }
+        }
+    }
+
+    /// Leaves the multicast group `group` on interface `interface` (an
+    /// interface index), setting `IP_DROP_MEMBERSHIP` for IPv4 or
+    /// `IPV6_LEAVE_GROUP` for IPv6.
+    pub fn leave_multicast(&self, group: IpAddr, interface: u32) -> Result<()> {
+        match group {
+            IpAddr::V4(v4) => {
+                let mreq = winapi::shared::ws2ipdef::IP_MREQ {
+                    imr_multiaddr: unsafe { mem::transmute(v4.octets()) },
+                    imr_interface: unsafe { mem::transmute(interface.to_be_bytes()) },
+                };
+                self.setsockopt(
+                    winapi::shared::ws2def::IPPROTO_IP as c_int,
+                    winapi::shared::ws2ipdef::IP_DROP_MEMBERSHIP,
+                    mreq,
+                )
+            }
+            IpAddr::V6(v6) => {
+                let mreq = winapi::shared::ws2ipdef::IPV6_MREQ {
+                    ipv6mr_multiaddr: unsafe { mem::transmute(v6.octets()) },
+                    ipv6mr_interface: interface,
+                };
+                self.setsockopt(
+                    winapi::shared::ws2def::IPPROTO_IPV6 as c_int,
+                    winapi::shared::ws2ipdef::IPV6_LEAVE_GROUP,
+                    mreq,
+                )
+            }
+        }
+    }
+
+    /// Sets whether outgoing multicast packets are looped back to this host.
+    pub fn set_multicast_loop(&self, loop_back: bool) -> Result<()> {
+        match self.family {
+            fam if fam == AF_INET as c_int => self.setsockopt(
+                winapi::shared::ws2def::IPPROTO_IP as c_int,
+                winapi::shared::ws2ipdef::IP_MULTICAST_LOOP,
+                loop_back as c_int,
+            ),
+            _ => self.setsockopt(
+                winapi::shared::ws2def::IPPROTO_IPV6 as c_int,
+                winapi::shared::ws2ipdef::IPV6_MULTICAST_LOOP,
+                loop_back as c_int,
+            ),
+        }
+    }
+
+    /// Sets the `IPV6_MULTICAST_HOPS` hop limit applied to outgoing
+    /// multicast packets.
+    pub fn set_multicast_hops(&self, hops: u32) -> Result<()> {
+        self.setsockopt(
+            winapi::shared::ws2def::IPPROTO_IPV6 as c_int,
+            winapi::shared::ws2ipdef::IPV6_MULTICAST_HOPS,
+            hops as c_int,
+        )
+    }
+
+    /// Sets the `IPV6_UNICAST_HOPS` hop limit applied to outgoing unicast
+    /// packets.
+    pub fn set_unicast_hops(&self, hops: u32) -> Result<()> {
+        self.setsockopt(
+            winapi::shared::ws2def::IPPROTO_IPV6 as c_int,
+            winapi::shared::ws2ipdef::IPV6_UNICAST_HOPS,
+            hops as c_int,
+        )
+    }
+
+    /// Receives a datagram along with its arrival interface, via
+    /// `WSARecvMsg` ancillary data.
+    ///
+    /// No hop-count option is enabled or parsed here, so `ttl` is always
+    /// `None` on Windows.
+    ///
+    /// Unlike Unix, Windows raw sockets have no error-queue equivalent of
+    /// `MSG_ERRQUEUE`, so `error` is always `None` here — an ICMP error for
+    /// a packet this socket sent instead surfaces as a failed `send`.
+    pub fn recv_with_info(&self, buf: &mut [u8]) -> Result<RecvInfo> {
+        self.enable_recv_info_options()?;
+        let wsa_recv_msg = self.load_wsa_recv_msg()?;
+
+        let mut peer: SOCKADDR_STORAGE = unsafe { mem::zeroed() };
+        let mut data = WSABUF {
+            len: buf.len() as u32,
+            buf: buf.as_mut_ptr() as *mut i8,
+        };
+        let mut control_buf = [0u8; 512];
+        let control = WSABUF {
+            len: control_buf.len() as u32,
+            buf: control_buf.as_mut_ptr() as *mut i8,
+        };
+
+        let mut msg = WSAMSG {
+            name: &mut peer as *mut SOCKADDR_STORAGE as *mut SOCKADDR,
+            namelen: mem::size_of::<SOCKADDR_STORAGE>() as i32,
+            lpBuffers: &mut data,
+            dwBufferCount: 1,
+            Control: control,
+            dwFlags: 0,
+        };
+
+        let mut received: u32 = 0;
+        let ret = unsafe {
+            wsa_recv_msg(self.sock, &mut msg, &mut received, ptr::null_mut(), None)
+        };
+
+        if ret != 0 {
+            return Err(last_os_error());
+        }
+
+        let source = sockaddr_to_ip(&peer, msg.namelen)?;
+        let mut info = RecvInfo {
+            len: received as usize,
+            source,
+            ttl: None,
+            interface: None,
+            error: None,
+        };
+
+        self.parse_cmsgs(&msg, &mut info);
+        Ok(info)
+    }
+
+    fn enable_recv_info_options(&self) -> Result<()> {
+        match self.family {
+            fam if fam == AF_INET as c_int => self.setsockopt(
+                winapi::shared::ws2def::IPPROTO_IP as c_int,
+                winapi::shared::ws2ipdef::IP_PKTINFO,
+                1 as c_int,
+            ),
+            _ => self.setsockopt(
+                winapi::shared::ws2def::IPPROTO_IPV6 as c_int,
+                winapi::shared::ws2ipdef::IPV6_PKTINFO,
+                1 as c_int,
+            ),
+        }
+    }
+
+    fn load_wsa_recv_msg(&self) -> Result<winapi::um::mswsock::LPFN_WSARECVMSG> {
+        let guid = winapi::um::mswsock::WSAID_WSARECVMSG;
+        let mut func: winapi::um::mswsock::LPFN_WSARECVMSG = None;
+        let mut bytes: u32 = 0;
+
+        let ret = unsafe {
+            winapi::um::winsock2::WSAIoctl(
+                self.sock,
+                winapi::um::mswsock::SIO_GET_EXTENSION_FUNCTION_POINTER,
+                &guid as *const _ as *mut c_void,
+                mem::size_of_val(&guid) as u32,
+                &mut func as *mut _ as *mut c_void,
+                mem::size_of::<winapi::um::mswsock::LPFN_WSARECVMSG>() as u32,
+                &mut bytes,
+                ptr::null_mut(),
+                None,
+            )
+        };
+
+        if ret != 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(func)
+    }
+
+    fn parse_cmsgs(&self, msg: &WSAMSG, info: &mut RecvInfo) {
+        // `WSACMSGHDR` has the same layout as Unix's `cmsghdr`: a length,
+        // level, and type, immediately followed by the option data, each
+        // entry aligned to the platform's pointer size.
+        let mut offset = 0usize;
+        let buf = unsafe {
+            std::slice::from_raw_parts(msg.Control.buf as *const u8, msg.Control.len as usize)
+        };
+
+        while offset + mem::size_of::<usize>() * 3 <= buf.len() {
+            let len = usize::from_ne_bytes(buf[offset..offset + mem::size_of::<usize>()].try_into().unwrap());
+            let level = i32::from_ne_bytes(buf[offset + 8..offset + 12].try_into().unwrap());
+            let ty = i32::from_ne_bytes(buf[offset + 12..offset + 16].try_into().unwrap());
+            let data_offset = offset + 16;
+
+            // `IN_PKTINFO`/`IN6_PKTINFO` hold the destination address first
+            // (4 or 16 bytes) and the interface index after it.
+            if level == winapi::shared::ws2def::IPPROTO_IP as i32
+                && ty == winapi::shared::ws2ipdef::IP_PKTINFO
+            {
+                if let Some(bytes) = buf[data_offset + 4..].get(..mem::size_of::<i32>()) {
+                    info.interface = Some(i32::from_ne_bytes(bytes.try_into().unwrap()) as u32);
+                }
+            } else if level == winapi::shared::ws2def::IPPROTO_IPV6 as i32
+                && ty == winapi::shared::ws2ipdef::IPV6_PKTINFO
+            {
+                if let Some(bytes) = buf[data_offset + 16..].get(..mem::size_of::<i32>()) {
+                    info.interface = Some(i32::from_ne_bytes(bytes.try_into().unwrap()) as u32);
+                }
+            }
+
+            if len == 0 {
+                break;
+            }
+            offset += (len + mem::size_of::<usize>() - 1) & !(mem::size_of::<usize>() - 1);
+        }
+    }
+
+    /// Sends `bufs` as a single datagram without copying them into a
+    /// contiguous buffer first, via `WSASend`.
+    pub fn send_vectored(&self, bufs: &[IoSlice]) -> Result<usize> {
+        let mut sent: u32 = 0;
+
+        let ret = unsafe {
+            winsock2::WSASend(
+                self.sock,
+                bufs.as_ptr() as *mut WSABUF,
+                bufs.len() as u32,
+                &mut sent,
+                0,
+                ptr::null_mut(),
+                None,
+            )
+        };
+
+        if ret != 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(sent as usize)
+    }
+
+    /// Receives a datagram into `bufs` without requiring a single
+    /// contiguous buffer, via `WSARecv`. The returned `RecvFlags` reports
+    /// whether the datagram was truncated to fit (Windows reports this as
+    /// the `WSAEMSGSIZE` error rather than a flag on the completed receive).
+    pub fn recv_vectored(&self, bufs: &mut [IoSliceMut]) -> Result<(usize, RecvFlags)> {
+        let mut received: u32 = 0;
+        let mut flags: u32 = 0;
+
+        let ret = unsafe {
+            winsock2::WSARecv(
+                self.sock,
+                bufs.as_mut_ptr() as *mut WSABUF,
+                bufs.len() as u32,
+                &mut received,
+                &mut flags,
+                ptr::null_mut(),
+                None,
+            )
+        };
+
+        if ret != 0 {
+            let err = last_os_error();
+
+            if err.raw_os_error() == Some(WSAEMSGSIZE as i32) {
+                // WSARecv doesn't reliably report a byte count on this
+                // failure path, but a truncated datagram by definition
+                // filled every supplied buffer before the remainder was
+                // discarded, so the buffers' total capacity is the count.
+                let len: usize = bufs.iter().map(|b| b.len()).sum();
+                return Ok((len, RecvFlags::new(true)));
+            }
+
+            return Err(err);
+        }
+
+        Ok((received as usize, RecvFlags::new(false)))
+    }
+
+    /// Returns the raw `SOCKET` handle backing this socket.
+    pub fn as_raw_socket(&self) -> RawSocket {
+        self.sock as RawSocket
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        unsafe {
+            winsock2::closesocket(self.sock);
+        }
+    }
+}
@@ -0,0 +1,767 @@
+//! Unix raw-socket backing for [`IcmpSocket`][crate::IcmpSocket].
+
+use std::io::{Error, IoSlice, IoSliceMut, Result};
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::unix::io::RawFd;
+use std::ptr;
+
+use libc::{c_int, c_void, sockaddr, sockaddr_in, sockaddr_in6, sockaddr_storage, socklen_t};
+
+use crate::info::{IcmpError, RecvFlags, RecvInfo};
+
+fn last_os_error() -> Error {
+    Error::last_os_error()
+}
+
+fn family_of(addr: IpAddr) -> c_int {
+    match addr {
+        IpAddr::V4(_) => libc::AF_INET,
+        IpAddr::V6(_) => libc::AF_INET6,
+    }
+}
+
+fn protocol_of(addr: IpAddr) -> c_int {
+    match addr {
+        IpAddr::V4(_) => libc::IPPROTO_ICMP,
+        IpAddr::V6(_) => libc::IPPROTO_ICMPV6,
+    }
+}
+
+/// Writes `addr` into a `sockaddr_storage` big enough for either address
+/// family, returning it alongside the length of the address actually
+/// written so callers don't have to know the concrete `sockaddr_in[6]` type.
+fn ip_to_sockaddr(addr: IpAddr) -> (sockaddr_storage, socklen_t) {
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+
+    let len = match addr {
+        IpAddr::V4(v4) => {
+            let sin = storage_as_mut::<sockaddr_in>(&mut storage);
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_addr.s_addr = u32::from_ne_bytes(v4.octets());
+            mem::size_of::<sockaddr_in>()
+        }
+        IpAddr::V6(v6) => {
+            let sin6 = storage_as_mut::<sockaddr_in6>(&mut storage);
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_addr.s6_addr = v6.octets();
+            mem::size_of::<sockaddr_in6>()
+        }
+    };
+
+    (storage, len as socklen_t)
+}
+
+fn storage_as_mut<T>(storage: &mut sockaddr_storage) -> &mut T {
+    unsafe { &mut *(storage as *mut sockaddr_storage as *mut T) }
+}
+
+fn sockaddr_to_ip(storage: &sockaddr_storage, len: socklen_t) -> Result<IpAddr> {
+    match storage.ss_family as c_int {
+        libc::AF_INET if len as usize >= mem::size_of::<sockaddr_in>() => unsafe {
+            let sin = &*(storage as *const sockaddr_storage as *const sockaddr_in);
+            Ok(IpAddr::V4(Ipv4Addr::from(sin.sin_addr.s_addr.to_ne_bytes())))
+        },
+        libc::AF_INET6 if len as usize >= mem::size_of::<sockaddr_in6>() => unsafe {
+            let sin6 = &*(storage as *const sockaddr_storage as *const sockaddr_in6);
+            Ok(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)))
+        },
+        _ => Err(Error::new(
+            std::io::ErrorKind::InvalidData,
+            "unrecognized sockaddr family",
+        )),
+    }
+}
+
+/// A raw ICMP socket.
+pub struct Socket {
+    fd: RawFd,
+    family: c_int,
+}
+
+impl Socket {
+    fn new(family: c_int, protocol: c_int) -> Result<Socket> {
+        let fd = unsafe { libc::socket(family, libc::SOCK_RAW, protocol) };
+
+        if fd < 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(Socket { fd, family })
+    }
+
+    /// Creates a socket and connects it to `addr`.
+    pub fn connect(addr: IpAddr) -> Result<Socket> {
+        let socket = Socket::new(family_of(addr), protocol_of(addr))?;
+        let (sa, len) = ip_to_sockaddr(addr);
+
+        let ret = unsafe { libc::connect(socket.fd, &sa as *const sockaddr_storage as *const sockaddr, len) };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(socket)
+    }
+
+    /// Creates a socket bound to the local address `addr`, without
+    /// connecting it to any peer.
+    pub fn bind(addr: IpAddr) -> Result<Socket> {
+        let socket = Socket::new(family_of(addr), protocol_of(addr))?;
+        let (sa, len) = ip_to_sockaddr(addr);
+
+        let ret = unsafe { libc::bind(socket.fd, &sa as *const sockaddr_storage as *const sockaddr, len) };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(socket)
+    }
+
+    pub fn send(&self, buf: &[u8]) -> Result<usize> {
+        let ret = unsafe {
+            libc::send(self.fd, buf.as_ptr() as *const c_void, buf.len(), 0)
+        };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(ret as usize)
+    }
+
+    /// Sends `buf` to `dst`, without requiring the socket to be connected.
+    pub fn send_to(&self, buf: &[u8], dst: IpAddr) -> Result<usize> {
+        let (sa, len) = ip_to_sockaddr(dst);
+
+        let ret = unsafe {
+            libc::sendto(
+                self.fd,
+                buf.as_ptr() as *const c_void,
+                buf.len(),
+                0,
+                &sa as *const sockaddr_storage as *const sockaddr,
+                len,
+            )
+        };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(ret as usize)
+    }
+
+    pub fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        let ret = unsafe {
+            libc::recv(self.fd, buf.as_mut_ptr() as *mut c_void, buf.len(), 0)
+        };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(ret as usize)
+    }
+
+    pub fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, IpAddr)> {
+        let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+        let mut len = mem::size_of::<sockaddr_storage>() as socklen_t;
+
+        let ret = unsafe {
+            libc::recvfrom(
+                self.fd,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+                0,
+                &mut storage as *mut sockaddr_storage as *mut sockaddr,
+                &mut len as *mut socklen_t,
+            )
+        };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        let addr = sockaddr_to_ip(&storage, len)?;
+        Ok((ret as usize, addr))
+    }
+
+    pub(crate) fn setsockopt<T>(&self, level: c_int, opt: c_int, value: T) -> Result<()> {
+        let ret = unsafe {
+            libc::setsockopt(
+                self.fd,
+                level,
+                opt,
+                &value as *const T as *const c_void,
+                mem::size_of::<T>() as socklen_t,
+            )
+        };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn getsockopt<T: Copy>(&self, level: c_int, opt: c_int) -> Result<T> {
+        unsafe {
+            let mut value: T = mem::zeroed();
+            let mut len = mem::size_of::<T>() as socklen_t;
+
+            let ret = libc::getsockopt(
+                self.fd,
+                level,
+                opt,
+                &mut value as *mut T as *mut c_void,
+                &mut len as *mut socklen_t,
+            );
+
+            if ret < 0 {
+                return Err(last_os_error());
+            }
+
+            Ok(value)
+        }
+    }
+
+    pub fn set_ttl(&self, ttl: u32) -> Result<()> {
+        match self.family {
+            libc::AF_INET => self.setsockopt(libc::IPPROTO_IP, libc::IP_TTL, ttl as c_int),
+            _ => self.setsockopt(libc::IPPROTO_IPV6, libc::IPV6_UNICAST_HOPS, ttl as c_int),
+        }
+    }
+
+    pub fn ttl(&self) -> Result<u32> {
+        let ttl: c_int = match self.family {
+            libc::AF_INET => self.getsockopt(libc::IPPROTO_IP, libc::IP_TTL)?,
+            _ => self.getsockopt(libc::IPPROTO_IPV6, libc::IPV6_UNICAST_HOPS)?,
+        };
+
+        Ok(ttl as u32)
+    }
+
+    pub fn set_broadcast(&self, broadcast: bool) -> Result<()> {
+        self.setsockopt(libc::SOL_SOCKET, libc::SO_BROADCAST, broadcast as c_int)
+    }
+
+    pub fn broadcast(&self) -> Result<bool> {
+        let broadcast: c_int = self.getsockopt(libc::SOL_SOCKET, libc::SO_BROADCAST)?;
+        Ok(broadcast != 0)
+    }
+
+    /// Enables or disables non-blocking mode via `fcntl`/`O_NONBLOCK`.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        let flags = unsafe { libc::fcntl(self.fd, libc::F_GETFL) };
+
+        if flags < 0 {
+            return Err(last_os_error());
+        }
+
+        let flags = if nonblocking {
+            flags | libc::O_NONBLOCK
+        } else {
+            flags & !libc::O_NONBLOCK
+        };
+
+        let ret = unsafe { libc::fcntl(self.fd, libc::F_SETFL, flags) };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(())
+    }
+
+    /// Returns the raw file descriptor backing this socket.
+    pub fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+
+    pub fn set_qos(&self, qos: u8) -> Result<()> {
+        match self.family {
+            libc::AF_INET => self.setsockopt(libc::IPPROTO_IP, libc::IP_TOS, qos as c_int),
+            _ => self.setsockopt(libc::IPPROTO_IPV6, libc::IPV6_TCLASS, qos as c_int),
+        }
+    }
+
+    pub fn qos(&self) -> Result<u8> {
+        let qos: c_int = match self.family {
+            libc::AF_INET => self.getsockopt(libc::IPPROTO_IP, libc::IP_TOS)?,
+            _ => self.getsockopt(libc::IPPROTO_IPV6, libc::IPV6_TCLASS)?,
+        };
+
+        Ok(qos as u8)
+    }
+
+    /// Joins the multicast group `group` on interface `interface` (an
+    /// interface index), setting `IP_ADD_MEMBERSHIP` for IPv4 or
+    /// `IPV6_ADD_MEMBERSHIP` for IPv6.
+    pub fn join_multicast(&self, group: IpAddr, interface: u32) -> Result<()> {
+        match group {
+            IpAddr::V4(v4) => {
+                let mreq = libc::ip_mreqn {
+                    imr_multiaddr: libc::in_addr {
+                        s_addr: u32::from_ne_bytes(v4.octets()),
+                    },
+                    imr_address: libc::in_addr { s_addr: 0 },
+                    imr_ifindex: interface as c_int,
+                };
+                self.setsockopt(libc::IPPROTO_IP, libc::IP_ADD_MEMBERSHIP, mreq)
+            }
+            IpAddr::V6(v6) => {
+                let mreq = libc::ipv6_mreq {
+                    ipv6mr_multiaddr: libc::in6_addr {
+                        s6_addr: v6.octets(),
+                    },
+                    ipv6mr_interface: interface,
+                };
+                self.setsockopt(libc::IPPROTO_IPV6, libc::IPV6_ADD_MEMBERSHIP, mreq)
+            }
+        }
+    }
+
+    /// Leaves the multicast group `group` on interface `interface` (an
+    /// interface index), setting `IP_DROP_MEMBERSHIP` for IPv4 or
+    /// `IPV6_DROP_MEMBERSHIP` for IPv6.
+    pub fn leave_multicast(&self, group: IpAddr, interface: u32) -> Result<()> {
+        match group {
+            IpAddr::V4(v4) => {
+                let mreq = libc::ip_mreqn {
+                    imr_multiaddr: libc::in_addr {
+                        s_addr: u32::from_ne_bytes(v4.octets()),
+                    },
+                    imr_address: libc::in_addr { s_addr: 0 },
+                    imr_ifindex: interface as c_int,
+                };
+                self.setsockopt(libc::IPPROTO_IP, libc::IP_DROP_MEMBERSHIP, mreq)
+            }
+            IpAddr::V6(v6) => {
+                let mreq = libc::ipv6_mreq {
+                    ipv6mr_multiaddr: libc::in6_addr {
+                        s6_addr: v6.octets(),
+                    },
+                    ipv6mr_interface: interface,
+                };
+                self.setsockopt(libc::IPPROTO_IPV6, libc::IPV6_DROP_MEMBERSHIP, mreq)
+            }
+        }
+    }
+
+    /// Sets whether outgoing multicast packets are looped back to this host.
+    pub fn set_multicast_loop(&self, loop_back: bool) -> Result<()> {
+        match self.family {
+            libc::AF_INET => {
+                self.setsockopt(libc::IPPROTO_IP, libc::IP_MULTICAST_LOOP, loop_back as u8)
+            }
+            _ => self.setsockopt(
+                libc::IPPROTO_IPV6,
+                libc::IPV6_MULTICAST_LOOP,
+                loop_back as c_int,
+            ),
+        }
+    }
+
+    /// Sets the `IPV6_MULTICAST_HOPS` hop limit applied to outgoing
+    /// multicast packets.
+    pub fn set_multicast_hops(&self, hops: u32) -> Result<()> {
+        self.setsockopt(libc::IPPROTO_IPV6, libc::IPV6_MULTICAST_HOPS, hops as c_int)
+    }
+
+    /// Sets the `IPV6_UNICAST_HOPS` hop limit applied to outgoing unicast
+    /// packets.
+    pub fn set_unicast_hops(&self, hops: u32) -> Result<()> {
+        self.setsockopt(libc::IPPROTO_IPV6, libc::IPV6_UNICAST_HOPS, hops as c_int)
+    }
+
+    /// Receives a datagram along with its TTL, arrival interface, and any
+    /// pending ICMP error, via `recvmsg` ancillary data.
+    ///
+    /// This first drains the socket's error queue (`MSG_ERRQUEUE`), which is
+    /// where the kernel delivers ICMP errors (time-exceeded,
+    /// destination-unreachable) that a router sent back for a packet this
+    /// socket sent. If nothing is pending there, it falls back to a normal
+    /// receive and reports the TTL/interface the packet arrived with.
+    pub fn recv_with_info(&self, buf: &mut [u8]) -> Result<RecvInfo> {
+        self.enable_recv_info_options()?;
+
+        match self.recvmsg(buf, libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT) {
+            Ok(info) if info.error.is_some() => Ok(info),
+            _ => self.recvmsg(buf, 0),
+        }
+    }
+
+    fn enable_recv_info_options(&self) -> Result<()> {
+        match self.family {
+            libc::AF_INET => {
+                self.setsockopt(libc::IPPROTO_IP, libc::IP_RECVTTL, 1 as c_int)?;
+                self.setsockopt(libc::IPPROTO_IP, libc::IP_PKTINFO, 1 as c_int)?;
+                self.setsockopt(libc::IPPROTO_IP, libc::IP_RECVERR, 1 as c_int)
+            }
+            _ => {
+                self.setsockopt(libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT, 1 as c_int)?;
+                self.setsockopt(libc::IPPROTO_IPV6, libc::IPV6_RECVPKTINFO, 1 as c_int)?;
+                self.setsockopt(libc::IPPROTO_IPV6, libc::IPV6_RECVERR, 1 as c_int)
+            }
+        }
+    }
+
+    fn recvmsg(&self, buf: &mut [u8], flags: c_int) -> Result<RecvInfo> {
+        let mut peer: sockaddr_storage = unsafe { mem::zeroed() };
+        let mut iov = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut c_void,
+            iov_len: buf.len(),
+        };
+        let mut control = [0u8; 512];
+
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_name = &mut peer as *mut sockaddr_storage as *mut c_void;
+        msg.msg_namelen = mem::size_of::<sockaddr_storage>() as socklen_t;
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut c_void;
+        msg.msg_controllen = control.len() as _;
+
+        let ret = unsafe { libc::recvmsg(self.fd, &mut msg, flags) };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        let source = sockaddr_to_ip(&peer, msg.msg_namelen)?;
+        let mut info = RecvInfo {
+            len: ret as usize,
+            source,
+            ttl: None,
+            interface: None,
+            error: None,
+        };
+
+        parse_cmsgs(&msg, buf, &mut info, flags & libc::MSG_ERRQUEUE != 0);
+        Ok(info)
+    }
+
+    /// Sends `bufs` as a single datagram without copying them into a
+    /// contiguous buffer first, via `sendmsg`.
+    pub fn send_vectored(&self, bufs: &[IoSlice]) -> Result<usize> {
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_iov = bufs.as_ptr() as *mut libc::iovec;
+        msg.msg_iovlen = bufs.len() as _;
+
+        let ret = unsafe { libc::sendmsg(self.fd, &msg, 0) };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        Ok(ret as usize)
+    }
+
+    /// Receives a datagram into `bufs` without requiring a single
+    /// contiguous buffer, via `recvmsg`. The returned `RecvFlags` reports
+    /// whether the datagram was truncated to fit.
+    pub fn recv_vectored(&self, bufs: &mut [IoSliceMut]) -> Result<(usize, RecvFlags)> {
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_iov = bufs.as_mut_ptr() as *mut libc::iovec;
+        msg.msg_iovlen = bufs.len() as _;
+
+        let ret = unsafe { libc::recvmsg(self.fd, &mut msg, 0) };
+
+        if ret < 0 {
+            return Err(last_os_error());
+        }
+
+        let flags = RecvFlags::new(msg.msg_flags & libc::MSG_TRUNC != 0);
+        Ok((ret as usize, flags))
+    }
+}
+
+/// Walks the ancillary data attached to a `recvmsg` call, filling in `info`'s
+/// `ttl`/`interface`/`error` fields from whichever control messages the
+/// kernel attached.
+///
+/// `from_errqueue` gates `IP_RECVERR`/`IPV6_RECVERR` handling, since those
+/// cmsgs only carry a `sock_extended_err` when the receive came from the
+/// socket's error queue (`MSG_ERRQUEUE`) rather than a normal read.
+fn parse_cmsgs(msg: &libc::msghdr, buf: &[u8], info: &mut RecvInfo, from_errqueue: bool) {
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(msg);
+
+        while !cmsg.is_null() {
+            let level = (*cmsg).cmsg_level;
+            let ty = (*cmsg).cmsg_type;
+            let data = libc::CMSG_DATA(cmsg);
+
+            match (level, ty) {
+                (libc::IPPROTO_IP, libc::IP_TTL) | (libc::IPPROTO_IPV6, libc::IPV6_HOPLIMIT) => {
+                    let mut ttl: c_int = 0;
+                    ptr::copy_nonoverlapping(data, &mut ttl as *mut c_int as *mut u8, mem::size_of::<c_int>());
+                    info.ttl = Some(ttl as u8);
+                }
+                (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                    let mut pktinfo: libc::in_pktinfo = mem::zeroed();
+                    ptr::copy_nonoverlapping(
+                        data,
+                        &mut pktinfo as *mut libc::in_pktinfo as *mut u8,
+                        mem::size_of::<libc::in_pktinfo>(),
+                    );
+                    info.interface = Some(pktinfo.ipi_ifindex as u32);
+                }
+                (libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => {
+                    let mut pktinfo: libc::in6_pktinfo = mem::zeroed();
+                    ptr::copy_nonoverlapping(
+                        data,
+                        &mut pktinfo as *mut libc::in6_pktinfo as *mut u8,
+                        mem::size_of::<libc::in6_pktinfo>(),
+                    );
+                    info.interface = Some(pktinfo.ipi6_ifindex as u32);
+                }
+                (libc::IPPROTO_IP, libc::IP_RECVERR) | (libc::IPPROTO_IPV6, libc::IPV6_RECVERR)
+                    if from_errqueue =>
+                {
+                    info.error = parse_extended_err(cmsg, level == libc::IPPROTO_IPV6);
+                    if let Some(error) = info.error.as_mut() {
+                        // When reading from the error queue, the data the
+                        // kernel hands back via msg_iov is the offending
+                        // packet itself, not new payload.
+                        error.inner_packet = buf[..info.len].to_vec();
+                    }
+                }
+                _ => {}
+            }
+
+            cmsg = libc::CMSG_NXTHDR(msg as *const libc::msghdr as *mut libc::msghdr, cmsg);
+        }
+    }
+}
+
+/// Parses a `sock_extended_err` (and the offender address trailing it) out
+/// of an `IP_RECVERR`/`IPV6_RECVERR` control message.
+unsafe fn parse_extended_err(cmsg: *const libc::cmsghdr, is_v6: bool) -> Option<IcmpError> {
+    let data = libc::CMSG_DATA(cmsg);
+    let mut err: libc::sock_extended_err = mem::zeroed();
+    ptr::copy_nonoverlapping(
+        data,
+        &mut err as *mut libc::sock_extended_err as *mut u8,
+        mem::size_of::<libc::sock_extended_err>(),
+    );
+
+    // The address of the node that generated the error immediately follows
+    // the `sock_extended_err` in the same cmsg; if it isn't a recognized
+    // IPv4/IPv6 address, no error is returned.
+    let offender = data.add(mem::size_of::<libc::sock_extended_err>()) as *const sockaddr_storage;
+    let source = if is_v6 {
+        sockaddr_to_ip(&*offender, mem::size_of::<sockaddr_in6>() as socklen_t).ok()?
+    } else {
+        sockaddr_to_ip(&*offender, mem::size_of::<sockaddr_in>() as socklen_t).ok()?
+    };
+
+    Some(IcmpError {
+        source,
+        kind: err.ee_type,
+        code: err.ee_code,
+        inner_packet: Vec::new(),
+    })
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a `msghdr` backed by `control`, with a single cmsg of the given
+    /// level/type holding `payload`'s bytes as its data.
+    fn msghdr_with_cmsg(control: &mut Vec<u8>, level: c_int, ty: c_int, payload: &[u8]) -> libc::msghdr {
+        let space = unsafe { libc::CMSG_SPACE(payload.len() as u32) } as usize;
+        control.resize(space, 0);
+
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_control = control.as_mut_ptr() as *mut c_void;
+        msg.msg_controllen = space as _;
+
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_len = libc::CMSG_LEN(payload.len() as u32) as _;
+            (*cmsg).cmsg_level = level;
+            (*cmsg).cmsg_type = ty;
+            ptr::copy_nonoverlapping(payload.as_ptr(), libc::CMSG_DATA(cmsg), payload.len());
+        }
+
+        msg
+    }
+
+    #[test]
+    fn ip_to_sockaddr_round_trips_v4() {
+        let addr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+        let (storage, len) = ip_to_sockaddr(addr);
+
+        assert_eq!(sockaddr_to_ip(&storage, len).unwrap(), addr);
+    }
+
+    #[test]
+    fn ip_to_sockaddr_round_trips_v6() {
+        let addr = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+        let (storage, len) = ip_to_sockaddr(addr);
+
+        assert_eq!(sockaddr_to_ip(&storage, len).unwrap(), addr);
+    }
+
+    #[test]
+    fn ip_to_sockaddr_v6_does_not_truncate_into_a_v4_sized_sockaddr() {
+        // A sockaddr_in6 (28 bytes) doesn't fit in a bare sockaddr (16
+        // bytes); sockaddr_storage exists precisely so this doesn't truncate.
+        let addr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let (_, len) = ip_to_sockaddr(addr);
+
+        assert!(len as usize >= mem::size_of::<sockaddr_in6>());
+    }
+
+    #[test]
+    fn sockaddr_to_ip_rejects_an_unrecognized_family() {
+        let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+        storage.ss_family = 0xff;
+
+        assert!(sockaddr_to_ip(&storage, mem::size_of::<sockaddr_storage>() as socklen_t).is_err());
+    }
+
+    fn empty_info() -> RecvInfo {
+        RecvInfo {
+            len: 0,
+            source: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            ttl: None,
+            interface: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn parse_cmsgs_reads_ipv4_ttl() {
+        let mut control = Vec::new();
+        let ttl: c_int = 42;
+        let msg = msghdr_with_cmsg(&mut control, libc::IPPROTO_IP, libc::IP_TTL, unsafe {
+            std::slice::from_raw_parts(&ttl as *const c_int as *const u8, mem::size_of::<c_int>())
+        });
+
+        let mut info = empty_info();
+        parse_cmsgs(&msg, &[], &mut info, false);
+
+        assert_eq!(info.ttl, Some(42));
+    }
+
+    #[test]
+    fn parse_cmsgs_reads_ipv6_hoplimit() {
+        let mut control = Vec::new();
+        let hoplimit: c_int = 7;
+        let msg = msghdr_with_cmsg(&mut control, libc::IPPROTO_IPV6, libc::IPV6_HOPLIMIT, unsafe {
+            std::slice::from_raw_parts(&hoplimit as *const c_int as *const u8, mem::size_of::<c_int>())
+        });
+
+        let mut info = empty_info();
+        parse_cmsgs(&msg, &[], &mut info, false);
+
+        assert_eq!(info.ttl, Some(7));
+    }
+
+    #[test]
+    fn parse_cmsgs_reads_ipv4_pktinfo_interface() {
+        let mut control = Vec::new();
+        let mut pktinfo: libc::in_pktinfo = unsafe { mem::zeroed() };
+        pktinfo.ipi_ifindex = 3;
+        let msg = msghdr_with_cmsg(&mut control, libc::IPPROTO_IP, libc::IP_PKTINFO, unsafe {
+            std::slice::from_raw_parts(
+                &pktinfo as *const libc::in_pktinfo as *const u8,
+                mem::size_of::<libc::in_pktinfo>(),
+            )
+        });
+
+        let mut info = empty_info();
+        parse_cmsgs(&msg, &[], &mut info, false);
+
+        assert_eq!(info.interface, Some(3));
+    }
+
+    #[test]
+    fn parse_cmsgs_reads_ipv6_pktinfo_interface() {
+        let mut control = Vec::new();
+        let mut pktinfo: libc::in6_pktinfo = unsafe { mem::zeroed() };
+        pktinfo.ipi6_ifindex = 9;
+        let msg = msghdr_with_cmsg(&mut control, libc::IPPROTO_IPV6, libc::IPV6_PKTINFO, unsafe {
+            std::slice::from_raw_parts(
+                &pktinfo as *const libc::in6_pktinfo as *const u8,
+                mem::size_of::<libc::in6_pktinfo>(),
+            )
+        });
+
+        let mut info = empty_info();
+        parse_cmsgs(&msg, &[], &mut info, false);
+
+        assert_eq!(info.interface, Some(9));
+    }
+
+    #[test]
+    fn parse_cmsgs_ignores_recverr_outside_the_error_queue() {
+        let mut control = Vec::new();
+        let err: libc::sock_extended_err = unsafe { mem::zeroed() };
+        let msg = msghdr_with_cmsg(&mut control, libc::IPPROTO_IP, libc::IP_RECVERR, unsafe {
+            std::slice::from_raw_parts(
+                &err as *const libc::sock_extended_err as *const u8,
+                mem::size_of::<libc::sock_extended_err>(),
+            )
+        });
+
+        let mut info = empty_info();
+        parse_cmsgs(&msg, &[], &mut info, false);
+
+        assert!(info.error.is_none());
+    }
+
+    #[test]
+    fn parse_cmsgs_reads_extended_err_and_offender_from_the_error_queue() {
+        let mut control = Vec::new();
+        let mut err: libc::sock_extended_err = unsafe { mem::zeroed() };
+        err.ee_type = 11; // time exceeded
+        err.ee_code = 0;
+
+        let mut offender: sockaddr_storage = unsafe { mem::zeroed() };
+        {
+            let sin = storage_as_mut::<sockaddr_in>(&mut offender);
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_addr.s_addr = u32::from_ne_bytes(Ipv4Addr::new(192, 0, 2, 1).octets());
+        }
+
+        let mut payload = Vec::new();
+        payload.extend_from_slice(unsafe {
+            std::slice::from_raw_parts(
+                &err as *const libc::sock_extended_err as *const u8,
+                mem::size_of::<libc::sock_extended_err>(),
+            )
+        });
+        payload.extend_from_slice(unsafe {
+            std::slice::from_raw_parts(
+                &offender as *const sockaddr_storage as *const u8,
+                mem::size_of::<sockaddr_storage>(),
+            )
+        });
+
+        let msg = msghdr_with_cmsg(&mut control, libc::IPPROTO_IP, libc::IP_RECVERR, &payload);
+
+        let mut info = empty_info();
+        info.len = 4;
+        parse_cmsgs(&msg, &[9, 9, 9, 9], &mut info, true);
+
+        let error = info.error.expect("expected a parsed ICMP error");
+        assert_eq!(error.kind, 11);
+        assert_eq!(error.code, 0);
+        assert_eq!(error.source, IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+        assert_eq!(error.inner_packet, vec![9, 9, 9, 9]);
+    }
+}
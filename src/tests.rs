@@ -0,0 +1,102 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::message::{checksum, EchoReply, EchoRequest};
+
+#[test]
+fn checksum_of_all_zeros_is_all_ones() {
+    assert_eq!(checksum(&[0, 0, 0, 0, 0, 0, 0, 0]), 0xffff);
+}
+
+#[test]
+fn checksum_folds_carries() {
+    // 0xffff + 0xffff overflows 16 bits and must be folded back in.
+    assert_eq!(checksum(&[0xff, 0xff, 0xff, 0xff]), 0x0000);
+}
+
+#[test]
+fn checksum_pads_odd_length_with_a_trailing_zero() {
+    // 0xff00 (0xff padded with a trailing zero byte) one's-complemented.
+    assert_eq!(checksum(&[0xff]), 0x00ff);
+}
+
+fn build_icmpv4_echo_reply(ident: u16, seq: u16, payload: &[u8]) -> Vec<u8> {
+    let mut buf = vec![0, 0, 0, 0];
+    buf.extend_from_slice(&ident.to_be_bytes());
+    buf.extend_from_slice(&seq.to_be_bytes());
+    buf.extend_from_slice(payload);
+
+    let sum = checksum(&buf);
+    buf[2..4].copy_from_slice(&sum.to_be_bytes());
+    buf
+}
+
+#[test]
+fn echo_request_to_bytes_has_a_valid_v4_checksum() {
+    let request = EchoRequest {
+        ident: 42,
+        seq: 7,
+        payload: vec![1, 2, 3, 4],
+    };
+    let localhost = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    let bytes = request.to_bytes(localhost);
+
+    assert_eq!(checksum(&bytes), 0);
+}
+
+#[test]
+fn echo_request_to_bytes_leaves_v6_checksum_zeroed() {
+    let request = EchoRequest {
+        ident: 42,
+        seq: 7,
+        payload: vec![1, 2, 3, 4],
+    };
+    let localhost = IpAddr::V6(Ipv6Addr::LOCALHOST);
+    let bytes = request.to_bytes(localhost);
+
+    assert_eq!(&bytes[2..4], &[0u8, 0u8]);
+}
+
+#[test]
+fn echo_reply_round_trips_through_bytes() {
+    let localhost = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    let bytes = build_icmpv4_echo_reply(42, 7, &[1, 2, 3, 4]);
+
+    let reply = EchoReply::from_bytes(&bytes, localhost).unwrap();
+
+    assert_eq!(reply.ident, 42);
+    assert_eq!(reply.seq, 7);
+    assert_eq!(reply.payload, vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn echo_reply_strips_the_prepended_ipv4_header() {
+    let localhost = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    let icmp = build_icmpv4_echo_reply(42, 7, &[1, 2, 3, 4]);
+
+    // A 20-byte IPv4 header (version 4, IHL 5) as the kernel prepends to a
+    // SOCK_RAW/IPPROTO_ICMP read, with the ICMP message immediately after it.
+    let mut datagram = vec![0x45, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    datagram.extend_from_slice(&icmp);
+
+    let reply = EchoReply::from_bytes(&datagram, localhost).unwrap();
+
+    assert_eq!(reply.ident, 42);
+    assert_eq!(reply.seq, 7);
+    assert_eq!(reply.payload, vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn echo_reply_rejects_a_bad_checksum() {
+    let localhost = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    let mut bytes = build_icmpv4_echo_reply(42, 7, &[1, 2, 3, 4]);
+    bytes[3] ^= 0xff;
+
+    assert!(EchoReply::from_bytes(&bytes, localhost).is_err());
+}
+
+#[test]
+fn echo_reply_rejects_a_message_that_is_too_short() {
+    let localhost = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+
+    assert!(EchoReply::from_bytes(&[0, 0], localhost).is_err());
+}